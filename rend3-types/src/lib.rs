@@ -0,0 +1,260 @@
+//! Backend-agnostic light types shared between `rend3` and the code that feeds
+//! it instructions. These mirror the GPU-facing `Shader*Light` structs the
+//! renderer builds internally, but carry the user-authored values directly.
+
+use std::marker::PhantomData;
+
+use glam::Vec3;
+
+/// A weak, index-based handle to a resource owned by the renderer. `T` is a
+/// zero-sized tag distinguishing the resource kind at the type level.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct RawResourceHandle<T> {
+    /// Index of the resource in its manager's slot list.
+    pub idx: usize,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> RawResourceHandle<T> {
+    /// Wraps a raw slot index.
+    pub fn new(idx: usize) -> Self {
+        Self {
+            idx,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+// Hand-written so the handle stays `Copy`/`Clone` regardless of `T`.
+impl<T> Copy for RawResourceHandle<T> {}
+impl<T> Clone for RawResourceHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+/// Tag for [`RawDirectionalLightHandle`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum DirectionalLightTag {}
+/// Tag for [`RawPointLightHandle`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PointLightTag {}
+/// Tag for [`RawSpotLightHandle`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SpotLightTag {}
+
+pub type RawDirectionalLightHandle = RawResourceHandle<DirectionalLightTag>;
+pub type RawPointLightHandle = RawResourceHandle<PointLightTag>;
+pub type RawSpotLightHandle = RawResourceHandle<SpotLightTag>;
+
+/// How a shadow map is filtered when it is sampled in the lighting shader.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ShadowFilterMode {
+    /// A single hardware depth comparison; hard edges.
+    None,
+    /// Hardware 2x2 percentage-closer filtering.
+    Hardware,
+    /// N-tap percentage-closer filtering.
+    Pcf,
+    /// Percentage-closer soft shadows.
+    Pcss,
+}
+
+/// Per-light shadow tuning. Bias values combat acne and peter-panning; `filter`
+/// trades softness for cost.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ShadowSettings {
+    /// Constant depth bias applied to every comparison.
+    pub depth_bias: f32,
+    /// Slope-scaled bias along the surface normal.
+    pub normal_bias: f32,
+    /// How the map is filtered when sampled.
+    pub filter: ShadowFilterMode,
+    /// World-space size of the light, used by PCSS to size the penumbra.
+    pub light_size: f32,
+    /// PCF kernel radius in texels; also the upper bound on the PCSS kernel.
+    pub kernel_radius: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            depth_bias: 0.0,
+            normal_bias: 0.0,
+            filter: ShadowFilterMode::Hardware,
+            light_size: 1.0,
+            kernel_radius: 1.0,
+        }
+    }
+}
+
+/// A light infinitely far away, illuminating the whole scene from one direction.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DirectionalLight {
+    /// Linear color of the light.
+    pub color: Vec3,
+    /// Brightness multiplier applied to `color`.
+    pub intensity: f32,
+    /// Direction the light travels along.
+    pub direction: Vec3,
+    /// Edge length, in texels, of the shadow map allocated for this light.
+    pub resolution: u32,
+    /// Whether this light allocates an atlas frame and casts shadows.
+    pub casts_shadows: bool,
+    /// Bias and filtering used when sampling this light's shadow map.
+    pub shadow: ShadowSettings,
+}
+
+/// A sparse update to an existing [`DirectionalLight`]; `None` fields are left
+/// unchanged.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DirectionalLightChange {
+    pub color: Option<Vec3>,
+    pub intensity: Option<f32>,
+    pub direction: Option<Vec3>,
+    pub resolution: Option<u32>,
+    pub casts_shadows: Option<bool>,
+    pub shadow: Option<ShadowSettings>,
+}
+
+impl DirectionalLight {
+    /// Applies every set field of `change` in place.
+    pub fn update_from_changes(&mut self, change: DirectionalLightChange) {
+        if let Some(color) = change.color {
+            self.color = color;
+        }
+        if let Some(intensity) = change.intensity {
+            self.intensity = intensity;
+        }
+        if let Some(direction) = change.direction {
+            self.direction = direction;
+        }
+        if let Some(resolution) = change.resolution {
+            self.resolution = resolution;
+        }
+        if let Some(casts_shadows) = change.casts_shadows {
+            self.casts_shadows = casts_shadows;
+        }
+        if let Some(shadow) = change.shadow {
+            self.shadow = shadow;
+        }
+    }
+}
+
+/// An omnidirectional light radiating from a single point.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PointLight {
+    /// Linear color of the light.
+    pub color: Vec3,
+    /// Brightness multiplier applied to `color`.
+    pub intensity: f32,
+    /// World-space position of the light.
+    pub position: Vec3,
+    /// Maximum distance the light reaches; also the far plane of every face.
+    pub range: f32,
+    /// Edge length, in texels, of each of the six cube faces.
+    pub resolution: u32,
+}
+
+/// A sparse update to an existing [`PointLight`]; `None` fields are left
+/// unchanged.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PointLightChange {
+    pub color: Option<Vec3>,
+    pub intensity: Option<f32>,
+    pub position: Option<Vec3>,
+    pub range: Option<f32>,
+    pub resolution: Option<u32>,
+}
+
+impl PointLight {
+    /// Applies every set field of `change` in place.
+    pub fn update_from_changes(&mut self, change: PointLightChange) {
+        if let Some(color) = change.color {
+            self.color = color;
+        }
+        if let Some(intensity) = change.intensity {
+            self.intensity = intensity;
+        }
+        if let Some(position) = change.position {
+            self.position = position;
+        }
+        if let Some(range) = change.range {
+            self.range = range;
+        }
+        if let Some(resolution) = change.resolution {
+            self.resolution = resolution;
+        }
+    }
+}
+
+/// A light radiating from a point within a cone, like a torch or stage light.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SpotLight {
+    /// Linear color of the light.
+    pub color: Vec3,
+    /// Brightness multiplier applied to `color`.
+    pub intensity: f32,
+    /// World-space position of the light.
+    pub position: Vec3,
+    /// Direction the cone points along.
+    pub direction: Vec3,
+    /// Maximum distance the light reaches.
+    pub range: f32,
+    /// Half-angle, in radians, of the fully lit inner cone.
+    pub inner_angle: f32,
+    /// Half-angle, in radians, at which the light fully falls off.
+    pub outer_angle: f32,
+    /// Edge length, in texels, of the shadow map allocated for this light.
+    pub resolution: u32,
+    /// Whether this light allocates an atlas frame and casts shadows.
+    pub casts_shadows: bool,
+}
+
+/// A sparse update to an existing [`SpotLight`]; `None` fields are left
+/// unchanged.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SpotLightChange {
+    pub color: Option<Vec3>,
+    pub intensity: Option<f32>,
+    pub position: Option<Vec3>,
+    pub direction: Option<Vec3>,
+    pub range: Option<f32>,
+    pub inner_angle: Option<f32>,
+    pub outer_angle: Option<f32>,
+    pub resolution: Option<u32>,
+    pub casts_shadows: Option<bool>,
+}
+
+impl SpotLight {
+    /// Applies every set field of `change` in place.
+    pub fn update_from_changes(&mut self, change: SpotLightChange) {
+        if let Some(color) = change.color {
+            self.color = color;
+        }
+        if let Some(intensity) = change.intensity {
+            self.intensity = intensity;
+        }
+        if let Some(position) = change.position {
+            self.position = position;
+        }
+        if let Some(direction) = change.direction {
+            self.direction = direction;
+        }
+        if let Some(range) = change.range {
+            self.range = range;
+        }
+        if let Some(inner_angle) = change.inner_angle {
+            self.inner_angle = inner_angle;
+        }
+        if let Some(outer_angle) = change.outer_angle {
+            self.outer_angle = outer_angle;
+        }
+        if let Some(resolution) = change.resolution {
+            self.resolution = resolution;
+        }
+        if let Some(casts_shadows) = change.casts_shadows {
+            self.casts_shadows = casts_shadows;
+        }
+    }
+}
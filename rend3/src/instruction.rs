@@ -5,7 +5,8 @@ use parking_lot::Mutex;
 use rend3_types::{
     trait_supertrait_alias, ObjectChange, PointLight, PointLightChange, RawDirectionalLightHandle,
     RawGraphDataHandleUntyped, RawMaterialHandle, RawMeshHandle, RawPointLightHandle, RawSkeletonHandle,
-    RawTexture2DHandle, RawTextureCubeHandle, TextureFromTexture, WasmNotSend, WasmNotSync,
+    RawSpotLightHandle, RawTexture2DHandle, RawTextureCubeHandle, SpotLight, SpotLightChange, TextureFromTexture,
+    WasmNotSend, WasmNotSync,
 };
 use wgpu::{CommandBuffer, Device};
 
@@ -62,6 +63,10 @@ pub enum InstructionKind {
         handle: RawPointLightHandle,
         light: PointLight,
     },
+    AddSpotLight {
+        handle: RawSpotLightHandle,
+        light: SpotLight,
+    },
     AddGraphData {
         add_invoke: Box<dyn AddGraphDataAddInvoke>,
     },
@@ -77,6 +82,10 @@ pub enum InstructionKind {
         handle: RawPointLightHandle,
         change: PointLightChange,
     },
+    ChangeSpotLight {
+        handle: RawSpotLightHandle,
+        change: SpotLightChange,
+    },
     DeleteMesh {
         handle: RawMeshHandle,
     },
@@ -101,6 +110,9 @@ pub enum InstructionKind {
     DeletePointLight {
         handle: RawPointLightHandle,
     },
+    DeleteSpotLight {
+        handle: RawSpotLightHandle,
+    },
     DeleteGraphData {
         handle: RawGraphDataHandleUntyped,
     },
@@ -202,6 +214,12 @@ impl DeletableRawResourceHandle for RawPointLightHandle {
     }
 }
 
+impl DeletableRawResourceHandle for RawSpotLightHandle {
+    fn into_delete_instruction_kind(self) -> InstructionKind {
+        InstructionKind::DeleteSpotLight { handle: self }
+    }
+}
+
 impl DeletableRawResourceHandle for RawGraphDataHandleUntyped {
     fn into_delete_instruction_kind(self) -> InstructionKind {
         InstructionKind::DeleteGraphData { handle: self }
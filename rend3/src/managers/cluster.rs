@@ -0,0 +1,244 @@
+use encase::ShaderType;
+use glam::{Mat4, UVec2, UVec3, Vec2, Vec3, Vec4, Vec4Swizzles};
+use wgpu::{
+    BindGroup, BindingType, Buffer, BufferBindingType, BufferDescriptor, BufferUsages, ComputePass, ComputePipeline,
+    Device, Queue, ShaderStages,
+};
+
+use crate::{
+    util::{
+        bind_merge::{BindGroupBuilder, BindGroupLayoutBuilder},
+        buffer::WrappedPotBuffer,
+    },
+    Renderer,
+};
+
+/// Cluster grid dimensions. Depth is sliced exponentially so near clusters are
+/// thin and far ones are deep.
+const CLUSTERS: UVec3 = UVec3::new(16, 9, 24);
+/// Upper bound on the number of lights recorded for a single cluster.
+const MAX_LIGHTS_PER_CLUSTER: u32 = 100;
+
+/// Clusters culled per compute workgroup; the dispatch rounds up to cover them
+/// all. Must match the `@workgroup_size` the culling shader declares.
+const CULL_WORKGROUP_SIZE: u32 = 64;
+
+fn cluster_count() -> u32 {
+    CLUSTERS.x * CLUSTERS.y * CLUSTERS.z
+}
+
+/// View-space bounding box of a single cluster, rebuilt whenever the
+/// resolution, projection or depth range changes and consumed by the culling
+/// compute shader.
+#[derive(Debug, Copy, Clone, ShaderType)]
+struct ClusterAabb {
+    min: Vec3,
+    max: Vec3,
+}
+
+#[derive(Debug, Clone, ShaderType)]
+struct ClusterAabbBuffer {
+    #[size(runtime)]
+    array: Vec<ClusterAabb>,
+}
+
+/// Constants the compute and forward shaders need to locate a fragment's
+/// cluster from `gl_FragCoord`/depth.
+#[derive(Debug, Clone, ShaderType)]
+struct ClusterUniform {
+    counts: UVec3,
+    z_near: f32,
+    z_far: f32,
+    max_lights_per_cluster: u32,
+    screen_dimensions: Vec2,
+    inv_projection: Mat4,
+}
+
+/// Owns the cluster grid and the two storage buffers the culling pass writes:
+/// a compacted per-cluster light-index list and per-cluster offsets/counts.
+pub struct ClusteredLightManager {
+    aabb_buffer: WrappedPotBuffer<ClusterAabbBuffer>,
+    uniform_buffer: WrappedPotBuffer<ClusterUniform>,
+    /// Compacted light indices, written by the culling compute shader.
+    light_index_list: Buffer,
+    /// `(offset, count)` into `light_index_list` for every cluster.
+    cluster_offsets: Buffer,
+
+    // Inputs the cluster AABBs depend on; the grid is rebuilt whenever any of
+    // them changes, not just the resolution.
+    resolution: UVec2,
+    projection: Mat4,
+    z_near: f32,
+    z_far: f32,
+}
+impl ClusteredLightManager {
+    pub fn new(device: &Device) -> Self {
+        profiling::scope!("ClusteredLightManager::new");
+
+        let light_index_list = device.create_buffer(&BufferDescriptor {
+            label: Some("cluster light index list"),
+            size: (cluster_count() * MAX_LIGHTS_PER_CLUSTER) as u64 * 4,
+            usage: BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let cluster_offsets = device.create_buffer(&BufferDescriptor {
+            label: Some("cluster offsets"),
+            size: cluster_count() as u64 * 2 * 4,
+            usage: BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            aabb_buffer: WrappedPotBuffer::new(device, BufferUsages::STORAGE, "cluster aabbs"),
+            uniform_buffer: WrappedPotBuffer::new(device, BufferUsages::UNIFORM, "cluster uniform"),
+            light_index_list,
+            cluster_offsets,
+            resolution: UVec2::ZERO,
+            projection: Mat4::ZERO,
+            z_near: 0.0,
+            z_far: 0.0,
+        }
+    }
+
+    /// Rebuilds the per-cluster AABBs when any input they depend on changes. The
+    /// grid itself is fixed, but each cluster's view-space bounds depend on the
+    /// resolution, projection, and depth range, so all of them gate the rebuild.
+    pub fn resize(&mut self, renderer: &Renderer, resolution: UVec2, projection: Mat4, z_near: f32, z_far: f32) {
+        if resolution == self.resolution
+            && projection == self.projection
+            && z_near == self.z_near
+            && z_far == self.z_far
+        {
+            return;
+        }
+        profiling::scope!("ClusteredLightManager::resize");
+        self.resolution = resolution;
+        self.projection = projection;
+        self.z_near = z_near;
+        self.z_far = z_far;
+
+        let inv_projection = projection.inverse();
+        let screen = resolution.as_vec2();
+        let tile = screen / Vec2::new(CLUSTERS.x as f32, CLUSTERS.y as f32);
+
+        let mut aabbs = Vec::with_capacity(cluster_count() as usize);
+        for z in 0..CLUSTERS.z {
+            // z_slice = near * (far/near) ^ (k / depth_slices)
+            let plane_near = -z_near * (z_far / z_near).powf(z as f32 / CLUSTERS.z as f32);
+            let plane_far = -z_near * (z_far / z_near).powf((z + 1) as f32 / CLUSTERS.z as f32);
+            for y in 0..CLUSTERS.y {
+                for x in 0..CLUSTERS.x {
+                    let min_ss = Vec2::new(x as f32, y as f32) * tile;
+                    let max_ss = Vec2::new((x + 1) as f32, (y + 1) as f32) * tile;
+
+                    // Rays through the tile corners in view space.
+                    let min_view = screen_to_view(min_ss, screen, inv_projection);
+                    let max_view = screen_to_view(max_ss, screen, inv_projection);
+
+                    let min_near = line_intersect_z(min_view, plane_near);
+                    let min_far = line_intersect_z(min_view, plane_far);
+                    let max_near = line_intersect_z(max_view, plane_near);
+                    let max_far = line_intersect_z(max_view, plane_far);
+
+                    let lo = min_near.min(min_far).min(max_near.min(max_far));
+                    let hi = min_near.max(min_far).max(max_near.max(max_far));
+
+                    aabbs.push(ClusterAabb { min: lo, max: hi });
+                }
+            }
+        }
+
+        self.aabb_buffer
+            .write_to_buffer(&renderer.device, &renderer.queue, &ClusterAabbBuffer { array: aabbs });
+
+        self.write_uniform(&renderer.device, &renderer.queue, screen, z_near, z_far, inv_projection);
+    }
+
+    fn write_uniform(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        screen_dimensions: Vec2,
+        z_near: f32,
+        z_far: f32,
+        inv_projection: Mat4,
+    ) {
+        let uniform = ClusterUniform {
+            counts: CLUSTERS,
+            z_near,
+            z_far,
+            max_lights_per_cluster: MAX_LIGHTS_PER_CLUSTER,
+            screen_dimensions,
+            inv_projection,
+        };
+        self.uniform_buffer.write_to_buffer(device, queue, &uniform);
+    }
+
+    pub fn add_to_bgl(bglb: &mut BindGroupLayoutBuilder) {
+        // Cluster AABBs, compacted light indices, and per-cluster offsets.
+        for _ in 0..3 {
+            bglb.append(
+                ShaderStages::COMPUTE | ShaderStages::FRAGMENT,
+                BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                None,
+            );
+        }
+        bglb.append(
+            ShaderStages::COMPUTE | ShaderStages::FRAGMENT,
+            BindingType::Buffer {
+                ty: BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: Some(ClusterUniform::min_size()),
+            },
+            None,
+        );
+    }
+
+    pub fn add_to_bg<'a>(&'a self, bgb: &mut BindGroupBuilder<'a>) {
+        bgb.append_buffer(&self.aabb_buffer);
+        bgb.append_buffer(&self.light_index_list);
+        bgb.append_buffer(&self.cluster_offsets);
+        bgb.append_buffer(&self.uniform_buffer);
+    }
+
+    /// Records the light-culling dispatch that populates `light_index_list` and
+    /// `cluster_offsets`. The caller supplies the culling pipeline and a bind
+    /// group holding this manager's buffers (see [`add_to_bg`]) alongside the
+    /// light data; one invocation is launched per cluster, rounded up to whole
+    /// workgroups.
+    ///
+    /// [`add_to_bg`]: Self::add_to_bg
+    pub fn record_cull<'a>(
+        &self,
+        cpass: &mut ComputePass<'a>,
+        pipeline: &'a ComputePipeline,
+        bind_group: &'a BindGroup,
+    ) {
+        cpass.set_pipeline(pipeline);
+        cpass.set_bind_group(0, bind_group, &[]);
+        let workgroups = (cluster_count() + CULL_WORKGROUP_SIZE - 1) / CULL_WORKGROUP_SIZE;
+        cpass.dispatch_workgroups(workgroups, 1, 1);
+    }
+}
+
+/// Unprojects a screen-space point (at NDC depth -1) into view space. Callers
+/// only use the eye ray through the result, not its depth.
+fn screen_to_view(screen: Vec2, dimensions: Vec2, inv_projection: Mat4) -> Vec3 {
+    let ndc = Vec2::new(
+        screen.x / dimensions.x * 2.0 - 1.0,
+        1.0 - screen.y / dimensions.y * 2.0,
+    );
+    let view = inv_projection * Vec4::new(ndc.x, ndc.y, -1.0, 1.0);
+    view.xyz() / view.w
+}
+
+/// Intersects the ray from the eye through `point` with the plane `z = target`.
+fn line_intersect_z(point: Vec3, target: f32) -> Vec3 {
+    // Eye is at the origin in view space, so the ray is just `t * point`.
+    let t = target / point.z;
+    point * t
+}
@@ -1,6 +1,6 @@
 use encase::{ArrayLength, ShaderType};
 use glam::{Mat4, UVec2, Vec2, Vec3};
-use rend3_types::{DirectionalLightChange, RawDirectionalLightHandle};
+use rend3_types::{DirectionalLightChange, RawDirectionalLightHandle, RawPointLightHandle, RawSpotLightHandle};
 use wgpu::{
     BindingType, BufferBindingType, BufferUsages, Device, Extent3d, ShaderStages, TextureDescriptor, TextureDimension,
     TextureUsages, TextureView, TextureViewDescriptor,
@@ -8,7 +8,7 @@ use wgpu::{
 
 use crate::{
     managers::CameraState,
-    types::DirectionalLight,
+    types::{DirectionalLight, ShadowFilterMode},
     util::{
         bind_merge::{BindGroupBuilder, BindGroupLayoutBuilder},
         buffer::WrappedPotBuffer,
@@ -16,13 +16,74 @@ use crate::{
     Renderer, INTERNAL_SHADOW_DEPTH_FORMAT,
 };
 
-mod shadow_alloc;
+pub(crate) mod shadow_alloc;
 mod shadow_camera;
 
 pub use shadow_alloc::ShadowMap;
 
 const MINIMUM_SHADOW_MAP_SIZE: UVec2 = UVec2::splat(32);
 
+/// The byte handed to the shadow-sampling shader to branch on. Lives here rather
+/// than in `rend3_types` because it is purely a detail of how this renderer
+/// encodes [`ShadowFilterMode`] for its shaders.
+fn filter_mode_as_shader(mode: ShadowFilterMode) -> u32 {
+    match mode {
+        ShadowFilterMode::None => 0,
+        ShadowFilterMode::Hardware => 1,
+        ShadowFilterMode::Pcf => 2,
+        ShadowFilterMode::Pcss => 3,
+    }
+}
+
+/// Sentinel `filter_mode` for a light that contributes illumination but has no
+/// shadow map: the shader skips the shadow lookup and treats it as fully lit.
+const SHADER_FILTER_UNSHADOWED: u32 = u32::MAX;
+
+/// Number of taps in the Poisson-disc kernel uploaded to the shader.
+const POISSON_TAP_COUNT: usize = 32;
+
+/// A fixed Poisson-disc distribution of normalized points in the unit disc,
+/// uploaded once and shared by every light's PCF/PCSS lookup.
+const POISSON_DISC: [Vec2; POISSON_TAP_COUNT] = [
+    Vec2::new(-0.0130, 0.0201),
+    Vec2::new(0.3277, -0.1278),
+    Vec2::new(-0.2987, 0.2574),
+    Vec2::new(0.1647, 0.4310),
+    Vec2::new(-0.4629, -0.2058),
+    Vec2::new(0.5231, 0.2419),
+    Vec2::new(-0.1076, -0.5237),
+    Vec2::new(-0.5905, 0.1714),
+    Vec2::new(0.6463, -0.2746),
+    Vec2::new(0.0894, -0.7013),
+    Vec2::new(-0.6908, -0.3612),
+    Vec2::new(0.3814, 0.6321),
+    Vec2::new(-0.3401, 0.6724),
+    Vec2::new(0.7711, 0.1123),
+    Vec2::new(-0.7792, 0.3046),
+    Vec2::new(0.2138, -0.8032),
+    Vec2::new(-0.1389, 0.8451),
+    Vec2::new(0.6007, -0.5819),
+    Vec2::new(-0.6218, -0.6231),
+    Vec2::new(0.8732, -0.2167),
+    Vec2::new(-0.8693, -0.0714),
+    Vec2::new(0.4521, 0.8049),
+    Vec2::new(-0.4832, -0.8120),
+    Vec2::new(0.0419, 0.9412),
+    Vec2::new(0.8124, 0.5017),
+    Vec2::new(-0.8321, 0.4921),
+    Vec2::new(0.9431, 0.1892),
+    Vec2::new(-0.2312, -0.9231),
+    Vec2::new(0.7213, -0.6521),
+    Vec2::new(-0.9521, -0.2412),
+    Vec2::new(0.3219, -0.9312),
+    Vec2::new(-0.0521, -0.9821),
+];
+
+#[derive(Debug, Clone, ShaderType)]
+struct ShaderPoissonDisc {
+    taps: [Vec2; POISSON_TAP_COUNT],
+}
+
 /// Internal representation of a directional light.
 pub struct InternalDirectionalLight {
     pub inner: DirectionalLight,
@@ -50,18 +111,51 @@ struct ShaderDirectionalLight {
     pub atlas_offset: Vec2,
     /// [0, 1] size of the shadow map in the atlas.
     pub atlas_size: Vec2,
+    /// Constant depth bias applied during comparison.
+    pub depth_bias: f32,
+    /// Slope-scaled normal bias.
+    pub normal_bias: f32,
+    /// Filter mode byte; see [`filter_mode_as_shader`].
+    pub filter_mode: u32,
+    /// World-space light size driving PCSS penumbra estimation.
+    pub light_size: f32,
+    /// PCF kernel radius in texels.
+    pub kernel_radius: f32,
 }
 
 #[derive(Debug, Clone)]
 pub struct ShadowDesc {
-    pub map: ShadowMap,
+    pub map: ShadowMap<RawDirectionalLightHandle>,
     pub camera: CameraState,
 }
 
+/// Identifies which light a packed shadow frame belongs to. Every shadow-casting
+/// light type packs into one shared atlas, so the packer needs a single handle
+/// type that can name a directional light, one cube face of a point light, or a
+/// spot light.
+#[derive(Debug, Copy, Clone)]
+pub enum ShadowCaster {
+    Directional(RawDirectionalLightHandle),
+    PointFace { handle: RawPointLightHandle, face: usize },
+    Spot(RawSpotLightHandle),
+}
+
+/// The result of packing every light's shadow frames into the single shared
+/// atlas owned by [`DirectionalLightManager`]. The point and spot managers
+/// filter [`maps`](Self::maps) for their own frames when building their buffers.
+pub struct SharedShadowAtlas {
+    /// Dimensions of the shared atlas texture the frames were packed into.
+    pub atlas_size: UVec2,
+    /// Every placed frame, across all light types.
+    pub maps: Vec<ShadowMap<ShadowCaster>>,
+}
+
 /// Manages directional lights and their associated shadow maps.
 pub struct DirectionalLightManager {
     data: Vec<Option<InternalDirectionalLight>>,
     data_buffer: WrappedPotBuffer<ShaderDirectionalLightBuffer>,
+    poisson_buffer: WrappedPotBuffer<ShaderPoissonDisc>,
+    poisson_uploaded: bool,
 
     texture_size: UVec2,
     texture_view: TextureView,
@@ -76,6 +170,8 @@ impl DirectionalLightManager {
         Self {
             data: Vec::new(),
             data_buffer: WrappedPotBuffer::new(device, BufferUsages::STORAGE, "shadow data buffer"),
+            poisson_buffer: WrappedPotBuffer::new(device, BufferUsages::UNIFORM, "shadow poisson disc"),
+            poisson_uploaded: false,
             texture_size,
             texture_view,
         }
@@ -100,35 +196,62 @@ impl DirectionalLightManager {
         self.data[handle.idx].take().unwrap();
     }
 
-    pub fn evaluate(&mut self, renderer: &Renderer, user_camera: &CameraState) -> (UVec2, Vec<ShadowDesc>) {
+    /// Packs the directional lights together with the point and spot lights'
+    /// `extra` shadow requests into one shared atlas, resizing the shared
+    /// texture to fit, and returns every placed frame alongside the directional
+    /// shadow cameras. The point and spot managers consume the returned
+    /// [`SharedShadowAtlas`] to build their own buffers against the same atlas.
+    pub fn evaluate(
+        &mut self,
+        renderer: &Renderer,
+        user_camera: &CameraState,
+        extra: Vec<(ShadowCaster, u32)>,
+    ) -> (SharedShadowAtlas, Vec<ShadowDesc>) {
         profiling::scope!("DirectionalLightManager::evaluate");
 
-        let shadow_maps: Vec<_> = self
+        let mut shadow_maps: Vec<(ShadowCaster, u32)> = self
             .data
             .iter()
             .enumerate()
-            .filter_map(|(idx, light)| Some((RawDirectionalLightHandle::new(idx), light.as_ref()?.inner.resolution)))
+            .filter_map(|(idx, light)| {
+                let light = light.as_ref()?;
+                // A light can contribute illumination without casting shadows;
+                // skip it here so it never claims an atlas frame.
+                light.inner.casts_shadows.then_some(())?;
+                Some((
+                    ShadowCaster::Directional(RawDirectionalLightHandle::new(idx)),
+                    light.inner.resolution,
+                ))
+            })
             .collect();
+        shadow_maps.extend(extra);
         let shadow_atlas = shadow_alloc::allocate_shadow_atlas(shadow_maps, renderer.limits.max_texture_dimension_2d);
 
-        let new_shadow_map_size = match shadow_atlas {
+        let atlas_size = match shadow_atlas {
             Some(ref m) => m.texture_dimensions.max(MINIMUM_SHADOW_MAP_SIZE),
             None => MINIMUM_SHADOW_MAP_SIZE,
         };
-        let new_shadow_map_size_f32 = new_shadow_map_size.as_vec2();
+        let atlas_size_f32 = atlas_size.as_vec2();
 
-        if new_shadow_map_size != self.texture_size {
-            self.texture_size = new_shadow_map_size;
+        if atlas_size != self.texture_size {
+            self.texture_size = atlas_size;
             self.texture_view = create_shadow_texture(&renderer.device, self.texture_size);
         }
 
-        let coordinates = match shadow_atlas {
-            Some(m) => m.maps,
-            None => return (new_shadow_map_size, Vec::new()),
-        };
+        let maps = shadow_atlas.map(|m| m.maps).unwrap_or_default();
 
-        let shadow_data: Vec<_> = coordinates
-            .into_iter()
+        // Pull out just the directional frames to build this manager's cameras
+        // and buffer; the rest travel back to the point and spot managers.
+        let shadow_data: Vec<_> = maps
+            .iter()
+            .filter_map(|map| match map.handle {
+                ShadowCaster::Directional(handle) => Some(ShadowMap {
+                    handle,
+                    offset: map.offset,
+                    size: map.size,
+                }),
+                _ => None,
+            })
             .map(|map| {
                 let camera = shadow_camera::shadow_camera(self.data[map.handle.idx].as_ref().unwrap(), user_camera);
 
@@ -136,21 +259,36 @@ impl DirectionalLightManager {
             })
             .collect();
 
+        // Every present light goes in the buffer so it contributes illumination;
+        // a non-casting light (or one that did not get an atlas frame) carries a
+        // zero atlas rect and the unshadowed sentinel so the shader lights it
+        // without a shadow lookup.
         let buffer = ShaderDirectionalLightBuffer {
             count: ArrayLength,
-            array: shadow_data
+            array: self
+                .data
                 .iter()
-                .map(|desc| {
-                    let light = &self.data[desc.map.handle.idx].as_ref().unwrap().inner;
+                .enumerate()
+                .filter_map(|(idx, light)| {
+                    let light = &light.as_ref()?.inner;
+                    let shadow = shadow_data.iter().find(|desc| desc.map.handle.idx == idx);
 
-                    ShaderDirectionalLight {
-                        view_proj: desc.camera.view_proj(),
+                    Some(ShaderDirectionalLight {
+                        view_proj: shadow.map_or(Mat4::IDENTITY, |desc| desc.camera.view_proj()),
                         color: light.color * light.intensity,
                         direction: light.direction,
-                        inv_resolution: 1.0 / new_shadow_map_size_f32,
-                        atlas_offset: desc.map.offset.as_vec2() / new_shadow_map_size_f32,
-                        atlas_size: desc.map.size as f32 / new_shadow_map_size_f32,
-                    }
+                        inv_resolution: 1.0 / atlas_size_f32,
+                        atlas_offset: shadow.map_or(Vec2::ZERO, |desc| desc.map.offset.as_vec2() / atlas_size_f32),
+                        atlas_size: shadow.map_or(Vec2::ZERO, |desc| desc.map.size as f32 / atlas_size_f32),
+                        depth_bias: light.shadow.depth_bias,
+                        normal_bias: light.shadow.normal_bias,
+                        filter_mode: match shadow {
+                            Some(_) => filter_mode_as_shader(light.shadow.filter),
+                            None => SHADER_FILTER_UNSHADOWED,
+                        },
+                        light_size: light.shadow.light_size,
+                        kernel_radius: light.shadow.kernel_radius,
+                    })
                 })
                 .collect(),
         };
@@ -158,7 +296,22 @@ impl DirectionalLightManager {
         self.data_buffer
             .write_to_buffer(&renderer.device, &renderer.queue, &buffer);
 
-        (new_shadow_map_size, shadow_data)
+        // The Poisson-disc kernel is constant, so it only needs uploading once.
+        if !self.poisson_uploaded {
+            self.poisson_buffer.write_to_buffer(
+                &renderer.device,
+                &renderer.queue,
+                &ShaderPoissonDisc { taps: POISSON_DISC },
+            );
+            self.poisson_uploaded = true;
+        }
+
+        (SharedShadowAtlas { atlas_size, maps }, shadow_data)
+    }
+
+    /// The single shadow atlas texture every light type renders its frames into.
+    pub fn texture_view(&self) -> &TextureView {
+        &self.texture_view
     }
 
     pub fn add_to_bgl(bglb: &mut BindGroupLayoutBuilder) {
@@ -171,10 +324,20 @@ impl DirectionalLightManager {
             },
             None,
         );
+        bglb.append(
+            ShaderStages::VERTEX_FRAGMENT,
+            BindingType::Buffer {
+                ty: BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: Some(ShaderPoissonDisc::min_size()),
+            },
+            None,
+        );
     }
 
     pub fn add_to_bg<'a>(&'a self, bgb: &mut BindGroupBuilder<'a>) {
         bgb.append_buffer(&self.data_buffer);
+        bgb.append_buffer(&self.poisson_buffer);
     }
 }
 
@@ -0,0 +1,293 @@
+use glam::UVec2;
+
+/// A single square shadow map that has been given a home inside the shadow
+/// atlas. `handle` identifies whatever the caller is packing - a light, or a
+/// single cube face of a light.
+#[derive(Debug, Clone)]
+pub struct ShadowMap<H> {
+    pub handle: H,
+    pub offset: UVec2,
+    pub size: u32,
+}
+
+/// Result of packing a set of shadow maps into a single atlas texture.
+#[derive(Debug, Clone)]
+pub struct ShadowCoordinates<H> {
+    /// Tight extent actually covered by the placed maps, i.e. the smallest
+    /// dimensions the atlas texture must have. This is the bottom-left-most
+    /// bounding box of the placement, *not* rounded up to a power of two: the
+    /// packer searches power-of-two atlas sizes but returns the snug extent so
+    /// the texture stays as small as the occupancy allows.
+    pub texture_dimensions: UVec2,
+    pub maps: Vec<ShadowMap<H>>,
+}
+
+/// Packs the given square shadow maps into the smallest atlas that fits them.
+///
+/// Maps are placed largest-first with a skyline (bottom-left) packer, which
+/// keeps occupancy high and the atlas tight. The atlas starts just big enough
+/// for the largest map and grows by powers of two until everything fits or
+/// `max_texture_dimension_2d` is reached.
+///
+/// Returns `None` when there is nothing to pack.
+pub fn allocate_shadow_atlas<H: Clone>(
+    mut maps: Vec<(H, u32)>,
+    max_texture_dimension_2d: u32,
+) -> Option<ShadowCoordinates<H>> {
+    if maps.is_empty() {
+        return None;
+    }
+
+    // Largest first; ties keep insertion order for determinism.
+    maps.sort_by(|a, b| b.1.cmp(&a.1));
+
+    let mut dimension = maps[0].1.next_power_of_two();
+    loop {
+        if let Some(coords) = try_pack(&maps, dimension) {
+            return Some(coords);
+        }
+        if dimension >= max_texture_dimension_2d {
+            // Give it one last try clamped to the hardware limit; if that fails
+            // the caller asked for more than the GPU can store.
+            return try_pack(&maps, max_texture_dimension_2d);
+        }
+        dimension = (dimension * 2).min(max_texture_dimension_2d);
+    }
+}
+
+/// A horizontal run of the skyline at a constant height `y`, spanning
+/// `[x, x + width)`.
+#[derive(Debug, Copy, Clone)]
+struct Segment {
+    x: u32,
+    width: u32,
+    y: u32,
+}
+
+/// The skyline free-space representation: a sorted, gap-free list of segments
+/// covering `[0, width)`.
+struct Skyline {
+    width: u32,
+    segments: Vec<Segment>,
+}
+
+impl Skyline {
+    fn new(width: u32) -> Self {
+        Self {
+            width,
+            segments: vec![Segment { x: 0, width, y: 0 }],
+        }
+    }
+
+    /// Lowest `y` at which a `size`-wide rect fits starting at `segments[i].x`,
+    /// i.e. the max top of every segment the rect would span. `None` if it runs
+    /// off the right edge.
+    fn fit(&self, i: usize, size: u32) -> Option<u32> {
+        let x = self.segments[i].x;
+        if x + size > self.width {
+            return None;
+        }
+        let mut remaining = size as i64;
+        let mut y = 0;
+        let mut j = i;
+        while remaining > 0 {
+            let seg = self.segments.get(j)?;
+            y = y.max(seg.y);
+            remaining -= seg.width as i64;
+            j += 1;
+        }
+        Some(y)
+    }
+
+    /// Finds the placement minimizing `(y + size, x)`.
+    fn find(&self, size: u32) -> Option<UVec2> {
+        let mut best: Option<(u32, u32)> = None;
+        for i in 0..self.segments.len() {
+            if let Some(y) = self.fit(i, size) {
+                let x = self.segments[i].x;
+                let key = (y + size, x);
+                if best.map_or(true, |b| key < (b.0 + size, b.1)) {
+                    best = Some((y, x));
+                }
+            }
+        }
+        best.map(|(y, x)| UVec2::new(x, y))
+    }
+
+    /// Raises the skyline to `top` across `[x, x + width)`, splitting partly
+    /// covered segments and merging adjacent segments of equal height.
+    fn raise(&mut self, x: u32, width: u32, top: u32) {
+        let end = x + width;
+        let mut result = Vec::with_capacity(self.segments.len() + 2);
+        let mut inserted = false;
+
+        for seg in &self.segments {
+            let seg_end = seg.x + seg.width;
+            if seg_end <= x || seg.x >= end {
+                result.push(*seg);
+                continue;
+            }
+            if seg.x < x {
+                result.push(Segment {
+                    x: seg.x,
+                    width: x - seg.x,
+                    y: seg.y,
+                });
+            }
+            if !inserted {
+                result.push(Segment { x, width, y: top });
+                inserted = true;
+            }
+            if seg_end > end {
+                result.push(Segment {
+                    x: end,
+                    width: seg_end - end,
+                    y: seg.y,
+                });
+            }
+        }
+        if !inserted {
+            result.push(Segment { x, width, y: top });
+        }
+
+        result.sort_by_key(|s| s.x);
+        self.segments = merge(result);
+    }
+}
+
+/// Coalesces adjacent segments that sit at the same height.
+fn merge(segments: Vec<Segment>) -> Vec<Segment> {
+    let mut out: Vec<Segment> = Vec::with_capacity(segments.len());
+    for seg in segments {
+        if let Some(last) = out.last_mut() {
+            if last.y == seg.y && last.x + last.width == seg.x {
+                last.width += seg.width;
+                continue;
+            }
+        }
+        out.push(seg);
+    }
+    out
+}
+
+fn try_pack<H: Clone>(maps: &[(H, u32)], dimension: u32) -> Option<ShadowCoordinates<H>> {
+    let mut skyline = Skyline::new(dimension);
+    let mut placed = Vec::with_capacity(maps.len());
+    let mut used = UVec2::ZERO;
+
+    for (handle, size) in maps {
+        let size = *size;
+        let offset = skyline.find(size)?;
+        if offset.y + size > dimension {
+            return None;
+        }
+        skyline.raise(offset.x, size, offset.y + size);
+
+        placed.push(ShadowMap {
+            handle: handle.clone(),
+            offset,
+            size,
+        });
+        used = used.max(UVec2::new(offset.x + size, offset.y + size));
+    }
+
+    Some(ShadowCoordinates {
+        texture_dimensions: used,
+        maps: placed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seg(x: u32, width: u32, y: u32) -> Segment {
+        Segment { x, width, y }
+    }
+
+    /// No two placed rects share any interior area.
+    fn no_overlap<H>(maps: &[ShadowMap<H>]) -> bool {
+        for (i, a) in maps.iter().enumerate() {
+            for b in &maps[i + 1..] {
+                let disjoint = a.offset.x + a.size <= b.offset.x
+                    || b.offset.x + b.size <= a.offset.x
+                    || a.offset.y + a.size <= b.offset.y
+                    || b.offset.y + b.size <= a.offset.y;
+                if !disjoint {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    #[test]
+    fn raise_splits_an_interior_segment() {
+        let mut skyline = Skyline::new(10);
+        skyline.raise(2, 3, 5);
+        let s = &skyline.segments;
+        assert_eq!(s.len(), 3);
+        assert_eq!((s[0].x, s[0].width, s[0].y), (0, 2, 0));
+        assert_eq!((s[1].x, s[1].width, s[1].y), (2, 3, 5));
+        assert_eq!((s[2].x, s[2].width, s[2].y), (5, 5, 0));
+    }
+
+    #[test]
+    fn raise_merges_equal_height_neighbours() {
+        let mut skyline = Skyline::new(10);
+        skyline.raise(2, 3, 5);
+        skyline.raise(5, 5, 5);
+        // The two ledges at height 5 must coalesce into one canonical segment.
+        let s = &skyline.segments;
+        assert_eq!(s.len(), 2);
+        assert_eq!((s[0].x, s[0].width, s[0].y), (0, 2, 0));
+        assert_eq!((s[1].x, s[1].width, s[1].y), (2, 8, 5));
+    }
+
+    #[test]
+    fn merge_coalesces_only_adjacent_equal_height() {
+        let merged = merge(vec![seg(0, 2, 0), seg(2, 3, 0), seg(5, 1, 4)]);
+        assert_eq!(merged.len(), 2);
+        assert_eq!((merged[0].x, merged[0].width, merged[0].y), (0, 5, 0));
+        assert_eq!((merged[1].x, merged[1].width, merged[1].y), (5, 1, 4));
+    }
+
+    #[test]
+    fn find_prefers_lowest_then_leftmost() {
+        let mut skyline = Skyline::new(8);
+        // Raise the left half; the lower right half is the preferred slot.
+        skyline.raise(0, 4, 4);
+        assert_eq!(skyline.find(4), Some(UVec2::new(4, 0)));
+    }
+
+    #[test]
+    fn largest_first_placement_is_stable() {
+        // Deliberately out of order; the packer sorts descending first.
+        let coords = allocate_shadow_atlas(vec![(0u32, 32), (1, 64), (2, 32)], 1024).unwrap();
+        let biggest = coords.maps.iter().find(|m| m.handle == 1).unwrap();
+        assert_eq!(biggest.offset, UVec2::ZERO);
+        assert_eq!(biggest.size, 64);
+        assert!(no_overlap(&coords.maps));
+    }
+
+    #[test]
+    fn grows_by_powers_of_two_until_everything_fits() {
+        // Two 64px maps cannot share a 64px atlas, so the packer grows to 128px
+        // and lays them side by side.
+        let coords = allocate_shadow_atlas(vec![(0u32, 64), (1, 64)], 1024).unwrap();
+        assert_eq!(coords.texture_dimensions, UVec2::new(128, 64));
+        assert!(no_overlap(&coords.maps));
+    }
+
+    #[test]
+    fn returns_none_when_nothing_fits_under_the_limit() {
+        // Two 32px maps need 64px of height, but the hardware caps the atlas at
+        // 32px, so the pack must fail rather than exceed the limit.
+        assert!(allocate_shadow_atlas(vec![(0u32, 32), (1, 32)], 32).is_none());
+    }
+
+    #[test]
+    fn empty_input_returns_none() {
+        assert!(allocate_shadow_atlas(Vec::<(u32, u32)>::new(), 1024).is_none());
+    }
+}
@@ -0,0 +1,217 @@
+use encase::{ArrayLength, ShaderType};
+use glam::{Mat4, UVec2, Vec2, Vec3};
+use rend3_types::{PointLightChange, RawPointLightHandle};
+use wgpu::{BindingType, BufferBindingType, BufferUsages, Device, ShaderStages};
+
+use crate::{
+    types::PointLight,
+    util::{
+        bind_merge::{BindGroupBuilder, BindGroupLayoutBuilder},
+        buffer::WrappedPotBuffer,
+    },
+    Renderer,
+};
+
+use super::directional::{shadow_alloc, ShadowCaster};
+
+/// A point light's shadow needs six perspective faces of a cube map. Each face
+/// is packed into the atlas as its own square frame.
+const SHADOW_FACES: usize = 6;
+
+/// Direction / up vector for each cube face, matching the usual
+/// +X, -X, +Y, -Y, +Z, -Z face order.
+const CUBE_FACES: [(Vec3, Vec3); SHADOW_FACES] = [
+    (Vec3::X, Vec3::NEG_Y),
+    (Vec3::NEG_X, Vec3::NEG_Y),
+    (Vec3::Y, Vec3::Z),
+    (Vec3::NEG_Y, Vec3::NEG_Z),
+    (Vec3::Z, Vec3::NEG_Y),
+    (Vec3::NEG_Z, Vec3::NEG_Y),
+];
+
+/// Internal representation of a point light.
+pub struct InternalPointLight {
+    pub inner: PointLight,
+}
+
+#[derive(Debug, Clone, ShaderType)]
+struct ShaderPointLightBuffer {
+    count: ArrayLength,
+    #[size(runtime)]
+    array: Vec<ShaderPointLight>,
+}
+
+#[derive(Debug, Copy, Clone, ShaderType)]
+struct ShaderPointLight {
+    /// View/Projection of each of the six cube faces. The fragment shader picks
+    /// a face from the dominant axis of the light-to-fragment vector.
+    pub view_proj: [Mat4; SHADOW_FACES],
+    /// [0, 1] offset of each face in the atlas.
+    pub atlas_offset: [Vec2; SHADOW_FACES],
+    /// [0, 1] size of each face in the atlas.
+    pub atlas_size: [Vec2; SHADOW_FACES],
+    /// Color/intensity of the light.
+    pub color: Vec3,
+    /// World-space position of the light.
+    pub position: Vec3,
+    /// Maximum distance the light reaches; also the far plane of every face.
+    pub range: f32,
+    /// 1 / resolution of whole shadow map.
+    pub inv_resolution: Vec2,
+}
+
+/// One allocated cube face of a point light, paired with the view-projection it
+/// should render with.
+pub struct PointShadowDesc {
+    pub map: shadow_alloc::ShadowMap<RawPointLightHandle>,
+    /// Which of the six cube faces this frame is.
+    pub face: usize,
+    pub view_proj: Mat4,
+}
+
+/// Manages point lights and their associated cube shadow maps.
+pub struct PointLightManager {
+    data: Vec<Option<InternalPointLight>>,
+    data_buffer: WrappedPotBuffer<ShaderPointLightBuffer>,
+}
+impl PointLightManager {
+    pub fn new(device: &Device) -> Self {
+        profiling::scope!("PointLightManager::new");
+
+        Self {
+            data: Vec::new(),
+            data_buffer: WrappedPotBuffer::new(device, BufferUsages::STORAGE, "point light data buffer"),
+        }
+    }
+
+    pub fn add(&mut self, handle: RawPointLightHandle, light: PointLight) {
+        if handle.idx >= self.data.len() {
+            self.data.resize_with(handle.idx + 1, || None);
+        }
+        self.data[handle.idx] = Some(InternalPointLight { inner: light })
+    }
+
+    pub fn update(&mut self, handle: RawPointLightHandle, change: PointLightChange) {
+        self.data[handle.idx]
+            .as_mut()
+            .unwrap()
+            .inner
+            .update_from_changes(change);
+    }
+
+    pub fn remove(&mut self, handle: RawPointLightHandle) {
+        self.data[handle.idx].take().unwrap();
+    }
+
+    /// The six cube faces each shadow-casting point light needs packed into the
+    /// shared atlas. Gathered by the render graph before the atlas is allocated.
+    pub fn shadow_requests(&self) -> Vec<(ShadowCaster, u32)> {
+        self.data
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, light)| {
+                let light = light.as_ref()?;
+                let handle = RawPointLightHandle::new(idx);
+                Some((0..SHADOW_FACES).map(move |face| (ShadowCaster::PointFace { handle, face }, light.inner.resolution)))
+            })
+            .flatten()
+            .collect()
+    }
+
+    /// Records a view-projection for each of this manager's faces in the shared
+    /// atlas and writes the light buffer, normalizing atlas coordinates by the
+    /// shared atlas dimensions so every light samples the correct sub-rect.
+    pub fn evaluate(
+        &mut self,
+        renderer: &Renderer,
+        atlas_size: UVec2,
+        maps: &[shadow_alloc::ShadowMap<ShadowCaster>],
+    ) -> Vec<PointShadowDesc> {
+        profiling::scope!("PointLightManager::evaluate");
+
+        let atlas_size_f32 = atlas_size.as_vec2();
+
+        let shadow_data: Vec<PointShadowDesc> = maps
+            .iter()
+            .filter_map(|map| {
+                let (handle, face) = match map.handle {
+                    ShadowCaster::PointFace { handle, face } => (handle, face),
+                    _ => return None,
+                };
+                let light = &self.data[handle.idx].as_ref().unwrap().inner;
+                let view_proj = face_view_proj(light.position, face, light.range);
+
+                Some(PointShadowDesc {
+                    map: shadow_alloc::ShadowMap {
+                        handle,
+                        offset: map.offset,
+                        size: map.size,
+                    },
+                    face,
+                    view_proj,
+                })
+            })
+            .collect();
+
+        let buffer = ShaderPointLightBuffer {
+            count: ArrayLength,
+            array: self
+                .data
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, light)| {
+                    let light = &light.as_ref()?.inner;
+
+                    let mut view_proj = [Mat4::IDENTITY; SHADOW_FACES];
+                    let mut atlas_offset = [Vec2::ZERO; SHADOW_FACES];
+                    let mut atlas_size_frac = [Vec2::ZERO; SHADOW_FACES];
+                    for desc in shadow_data.iter().filter(|d| d.map.handle.idx == idx) {
+                        let face = desc.face;
+                        view_proj[face] = desc.view_proj;
+                        atlas_offset[face] = desc.map.offset.as_vec2() / atlas_size_f32;
+                        atlas_size_frac[face] = Vec2::splat(desc.map.size as f32) / atlas_size_f32;
+                    }
+
+                    Some(ShaderPointLight {
+                        view_proj,
+                        atlas_offset,
+                        atlas_size: atlas_size_frac,
+                        color: light.color * light.intensity,
+                        position: light.position,
+                        range: light.range,
+                        inv_resolution: 1.0 / atlas_size_f32,
+                    })
+                })
+                .collect(),
+        };
+
+        self.data_buffer
+            .write_to_buffer(&renderer.device, &renderer.queue, &buffer);
+
+        shadow_data
+    }
+
+    pub fn add_to_bgl(bglb: &mut BindGroupLayoutBuilder) {
+        bglb.append(
+            ShaderStages::VERTEX_FRAGMENT,
+            BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: Some(ShaderPointLightBuffer::min_size()),
+            },
+            None,
+        );
+    }
+
+    pub fn add_to_bg<'a>(&'a self, bgb: &mut BindGroupBuilder<'a>) {
+        bgb.append_buffer(&self.data_buffer);
+    }
+}
+
+/// Builds the 90° FOV view-projection for one cube face of a point light.
+fn face_view_proj(position: Vec3, face: usize, range: f32) -> Mat4 {
+    let (direction, up) = CUBE_FACES[face];
+    let proj = Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1.0, 0.1, range);
+    let view = Mat4::look_to_rh(position, direction, up);
+    proj * view
+}
@@ -0,0 +1,205 @@
+use encase::{ArrayLength, ShaderType};
+use glam::{Mat4, UVec2, Vec2, Vec3};
+use rend3_types::{RawSpotLightHandle, SpotLightChange};
+use wgpu::{BindingType, BufferBindingType, BufferUsages, Device, ShaderStages};
+
+use crate::{
+    types::SpotLight,
+    util::{
+        bind_merge::{BindGroupBuilder, BindGroupLayoutBuilder},
+        buffer::WrappedPotBuffer,
+    },
+    Renderer,
+};
+
+use super::directional::{shadow_alloc, ShadowCaster};
+
+/// Internal representation of a spot light.
+pub struct InternalSpotLight {
+    pub inner: SpotLight,
+}
+
+#[derive(Debug, Clone, ShaderType)]
+struct ShaderSpotLightBuffer {
+    count: ArrayLength,
+    #[size(runtime)]
+    array: Vec<ShaderSpotLight>,
+}
+
+#[derive(Debug, Copy, Clone, ShaderType)]
+struct ShaderSpotLight {
+    /// View/Projection of the single perspective frustum, using viewports so it
+    /// always outputs [-1, 1] no matter where in the atlas the shadow lives.
+    pub view_proj: Mat4,
+    /// Color/intensity of the light.
+    pub color: Vec3,
+    /// World-space position of the light.
+    pub position: Vec3,
+    /// Direction the cone points along.
+    pub direction: Vec3,
+    /// 1 / resolution of whole shadow map.
+    pub inv_resolution: Vec2,
+    /// [0, 1] offset of the shadow map in the atlas.
+    pub atlas_offset: Vec2,
+    /// [0, 1] size of the shadow map in the atlas.
+    pub atlas_size: Vec2,
+    /// Cosine of the inner cone half-angle; full intensity inside this.
+    pub inner_cone: f32,
+    /// Cosine of the outer cone half-angle; smoothstep falls to zero here.
+    pub outer_cone: f32,
+    /// Maximum distance the light reaches.
+    pub range: f32,
+}
+
+/// One spot light's allocated shadow frame and the camera that renders it.
+pub struct SpotShadowDesc {
+    pub map: shadow_alloc::ShadowMap<RawSpotLightHandle>,
+    pub view_proj: Mat4,
+}
+
+/// Manages spot lights and their associated shadow maps. A spot light needs
+/// only a single perspective frustum, so it maps onto one frame of the shared
+/// shadow atlas owned by the directional light manager.
+pub struct SpotLightManager {
+    data: Vec<Option<InternalSpotLight>>,
+    data_buffer: WrappedPotBuffer<ShaderSpotLightBuffer>,
+}
+impl SpotLightManager {
+    pub fn new(device: &Device) -> Self {
+        profiling::scope!("SpotLightManager::new");
+
+        Self {
+            data: Vec::new(),
+            data_buffer: WrappedPotBuffer::new(device, BufferUsages::STORAGE, "spot light data buffer"),
+        }
+    }
+
+    pub fn add(&mut self, handle: RawSpotLightHandle, light: SpotLight) {
+        if handle.idx >= self.data.len() {
+            self.data.resize_with(handle.idx + 1, || None);
+        }
+        self.data[handle.idx] = Some(InternalSpotLight { inner: light })
+    }
+
+    pub fn update(&mut self, handle: RawSpotLightHandle, change: SpotLightChange) {
+        self.data[handle.idx]
+            .as_mut()
+            .unwrap()
+            .inner
+            .update_from_changes(change);
+    }
+
+    pub fn remove(&mut self, handle: RawSpotLightHandle) {
+        self.data[handle.idx].take().unwrap();
+    }
+
+    /// One frame per shadow-casting spot light, to be packed into the shared
+    /// atlas. Gathered by the render graph before the atlas is allocated.
+    pub fn shadow_requests(&self) -> Vec<(ShadowCaster, u32)> {
+        self.data
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, light)| {
+                let light = light.as_ref()?;
+                light.inner.casts_shadows.then_some(())?;
+                Some((ShadowCaster::Spot(RawSpotLightHandle::new(idx)), light.inner.resolution))
+            })
+            .collect()
+    }
+
+    /// Records a view-projection for each of this manager's frames in the shared
+    /// atlas and writes the light buffer, normalizing atlas coordinates by the
+    /// shared atlas dimensions.
+    pub fn evaluate(
+        &mut self,
+        renderer: &Renderer,
+        atlas_size: UVec2,
+        maps: &[shadow_alloc::ShadowMap<ShadowCaster>],
+    ) -> Vec<SpotShadowDesc> {
+        profiling::scope!("SpotLightManager::evaluate");
+
+        let shadow_data: Vec<SpotShadowDesc> = maps
+            .iter()
+            .filter_map(|map| {
+                let handle = match map.handle {
+                    ShadowCaster::Spot(handle) => handle,
+                    _ => return None,
+                };
+                let light = &self.data[handle.idx].as_ref().unwrap().inner;
+                let view_proj = spot_view_proj(light.position, light.direction, light.outer_angle, light.range);
+
+                Some(SpotShadowDesc {
+                    map: shadow_alloc::ShadowMap {
+                        handle,
+                        offset: map.offset,
+                        size: map.size,
+                    },
+                    view_proj,
+                })
+            })
+            .collect();
+
+        self.write_buffer(renderer, &shadow_data, atlas_size.as_vec2());
+
+        shadow_data
+    }
+
+    fn write_buffer(&mut self, renderer: &Renderer, shadow_data: &[SpotShadowDesc], atlas_size: Vec2) {
+        // Every present spot goes in the buffer so it contributes illumination;
+        // a non-casting spot carries a zero atlas rect so the shader lights it
+        // without a shadow lookup.
+        let buffer = ShaderSpotLightBuffer {
+            count: ArrayLength,
+            array: self
+                .data
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, light)| {
+                    let light = &light.as_ref()?.inner;
+                    let shadow = shadow_data.iter().find(|desc| desc.map.handle.idx == idx);
+
+                    Some(ShaderSpotLight {
+                        view_proj: shadow.map_or(Mat4::IDENTITY, |desc| desc.view_proj),
+                        color: light.color * light.intensity,
+                        position: light.position,
+                        direction: light.direction,
+                        inv_resolution: 1.0 / atlas_size,
+                        atlas_offset: shadow.map_or(Vec2::ZERO, |desc| desc.map.offset.as_vec2() / atlas_size),
+                        atlas_size: shadow.map_or(Vec2::ZERO, |desc| Vec2::splat(desc.map.size as f32) / atlas_size),
+                        inner_cone: light.inner_angle.cos(),
+                        outer_cone: light.outer_angle.cos(),
+                        range: light.range,
+                    })
+                })
+                .collect(),
+        };
+
+        self.data_buffer
+            .write_to_buffer(&renderer.device, &renderer.queue, &buffer);
+    }
+
+    pub fn add_to_bgl(bglb: &mut BindGroupLayoutBuilder) {
+        bglb.append(
+            ShaderStages::VERTEX_FRAGMENT,
+            BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: Some(ShaderSpotLightBuffer::min_size()),
+            },
+            None,
+        );
+    }
+
+    pub fn add_to_bg<'a>(&'a self, bgb: &mut BindGroupBuilder<'a>) {
+        bgb.append_buffer(&self.data_buffer);
+    }
+}
+
+/// Builds the perspective view-projection for a spot light: the frustum opens
+/// to twice the outer cone angle so the whole lit cone is captured.
+fn spot_view_proj(position: Vec3, direction: Vec3, outer_angle: f32, range: f32) -> Mat4 {
+    let up = if direction.abs().dot(Vec3::Y) > 0.99 { Vec3::Z } else { Vec3::Y };
+    let proj = Mat4::perspective_rh(2.0 * outer_angle, 1.0, 0.1, range);
+    let view = Mat4::look_to_rh(position, direction, up);
+    proj * view
+}